@@ -0,0 +1,20 @@
+// Example of the built-in "stats" object macro library.
+//
+// Load it once with rs.load_builtins("stats") and then call the macros
+// below from any reply. Each macro keeps its running state in a single
+// user variable, so it survives across turns without storing a full
+// history of observations.
+
+! version = 2.0
+
++ track my response time *
+- Logged.<call>stats_mean response_time <star></call>
+
++ what is my average response time
+- Your average response time is <call>stats_mean response_time</call>.
+
++ track my latency *
+- Logged.<call>stats_quantile latency 0.9 <star></call>
+
++ what is my p90 latency
+- Your 90th-percentile latency is <call>stats_quantile latency 0.9</call>.
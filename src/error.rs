@@ -0,0 +1,29 @@
+use std::fmt;
+
+/// An error raised by an object-macro handler.
+///
+/// Mirrors the role of the Python side's own `RSErrors`/`ObjectError`
+/// style: handlers never panic across the FFI boundary, they report
+/// failures through this type and the caller decides how to surface them
+/// to the bot author (typically as the reply text `[ERR: ...]`).
+#[derive(Debug)]
+pub enum ObjectError {
+    /// Raised from [`crate::ObjectHandler::load`] when a macro's source
+    /// could not be prepared (e.g. it failed to compile).
+    Load(String),
+    /// Raised from [`crate::ObjectHandler::call`] when a previously
+    /// loaded macro could not be invoked, crashed, or returned something
+    /// that could not be coerced to a reply string.
+    Call(String),
+}
+
+impl fmt::Display for ObjectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ObjectError::Load(msg) => write!(f, "object macro failed to load: {msg}"),
+            ObjectError::Call(msg) => write!(f, "object macro call failed: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for ObjectError {}
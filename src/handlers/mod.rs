@@ -0,0 +1,47 @@
+//! Object macro handlers.
+//!
+//! A RiveScript brain may embed "object macros" written in a language
+//! other than RiveScript itself, e.g.:
+//!
+//! ```text
+//! > object add python
+//!     a, b = args
+//!     return int(a) + int(b)
+//! < object
+//! ```
+//!
+//! On the Python side, `rs.set_handler(language, handler)` registers an
+//! [`ObjectHandler`] for the language named after `object ... <language>`.
+//! The handlers in this module are the native (Rust-backed) ones that
+//! ship with this crate; pure-Python handlers such as `PyRiveObjects`
+//! keep living on the Python side and never need to cross the FFI
+//! boundary at all.
+
+mod rust_handler;
+mod sandbox;
+mod subprocess;
+
+pub use rust_handler::RustRiveObjects;
+pub use sandbox::SandboxedPython;
+pub use subprocess::SubprocessObjectHandler;
+
+use crate::error::ObjectError;
+use crate::RiveScript;
+
+/// The contract every object-macro language handler implements.
+///
+/// This is the native mirror of the Python `load(name, code)` /
+/// `call(rs, name, fields)` handler contract: the brain loader calls
+/// [`ObjectHandler::load`] once per `> object` block, then
+/// [`ObjectHandler::call`] each time a reply contains
+/// `<call>name arg1 arg2</call>`.
+pub trait ObjectHandler {
+    /// Prepare the macro named `name` from its RiveScript-embedded
+    /// source `code` so that it is ready to be called.
+    fn load(&mut self, name: &str, code: &str) -> Result<(), ObjectError>;
+
+    /// Invoke the macro named `name` with `fields` (the whitespace-split
+    /// arguments following `<call>name`), returning its result as a
+    /// string, coerced exactly as the Python handlers do.
+    fn call(&mut self, rs: &mut RiveScript, name: &str, fields: &[String]) -> Result<String, ObjectError>;
+}
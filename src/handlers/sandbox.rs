@@ -0,0 +1,408 @@
+use std::io::{BufRead, BufReader, Read, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use serde_json::{json, Value};
+
+use crate::error::ObjectError;
+use crate::handlers::ObjectHandler;
+use crate::RiveScript;
+
+/// An [`ObjectHandler`] that runs Python object macros in a separate,
+/// restricted worker process instead of `exec`ing them in the host
+/// interpreter.
+///
+/// The plain Python handler (`PyRiveObjects`) runs `> object ... python`
+/// code directly in-process, which is fine for macros the bot author
+/// wrote themselves but risky for brains pulled in from the community.
+/// `SandboxedPython` instead runs them in a separate worker process and
+/// talks to it over a line-delimited JSON protocol on its stdin/stdout:
+///
+/// * parent -> worker, on load:
+///   `{"command": "load", "name": ..., "source": ...}`, answered with
+///   `{}` or `{"error": "..."}`
+/// * parent -> worker: `{"call": name, "args": [...], "user": uid}`
+/// * worker -> parent: `{"result": "..."}` or `{"error": "..."}`
+/// * worker -> parent (mid-call): `{"rpc": "set_uservar", "params": [...]}`,
+///   which the parent fulfils against the live [`RiveScript`] instance
+///   and answers with `{"rpc_result": ...}` before the worker's call is
+///   allowed to finish.
+///
+/// `spawn()` itself only gives you process-level isolation: a stripped
+/// environment and the `-I -S` Python flags (isolated mode, no `site`
+/// imports, no env/cwd-driven `sys.path` mutation). It does **not**
+/// restrict builtins or imports — that has to happen inside the worker
+/// script, since it's the worker that `exec`s the macro body. This crate
+/// ships [`DEFAULT_WORKER_SCRIPT`] (and [`SandboxedPython::spawn_default`]
+/// to run it) as a reference worker that installs a restricted
+/// `__builtins__` dict and an import allowlist before running any macro,
+/// so a macro's own `import socket` / `import os` statements fail. That
+/// restriction covers builtins and `import` statements only: objects a
+/// macro can reach through attributes (the `rs` proxy's method globals,
+/// modules that an allowlisted module imports) are not blocked, and can
+/// lead back to the real `__import__`.
+pub struct SandboxedPython {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    /// Path to the restricted worker script passed to the interpreter.
+    worker_script: String,
+    /// The worker's stderr output, collected by a background thread that
+    /// keeps draining the pipe so a chatty worker (e.g. an uncaught
+    /// Python traceback) can never fill the stderr pipe buffer and block
+    /// the worker on a write — which would otherwise hang `recv` forever
+    /// waiting for a stdout line that will never come. The text is stored
+    /// here only once the worker closes its stderr, normally on exit.
+    stderr: Arc<Mutex<String>>,
+}
+
+/// A reference worker script enforcing the restriction `spawn()` alone
+/// doesn't: a restricted `__builtins__` dict (no `open`, `eval`,
+/// `__import__`, etc. beyond what's allowlisted below) and an import
+/// hook that only allows a small set of side-effect-free standard
+/// library modules to be imported by name. Attribute access is not
+/// restricted, so a macro that walks object attributes can still reach
+/// unrestricted modules (see [`SandboxedPython`]). Speaks the same `{"call"|"command": "load"}` /
+/// `{"result"|"error"}` / `{"rpc": ..., "params": [...]}` protocol
+/// documented on [`SandboxedPython`] itself.
+pub const DEFAULT_WORKER_SCRIPT: &str = r#"import sys, json, builtins
+
+ALLOWED_BUILTIN_NAMES = (
+    "abs", "all", "any", "bool", "dict", "enumerate", "float", "int",
+    "len", "list", "max", "min", "range", "repr", "reversed", "round",
+    "set", "sorted", "str", "sum", "tuple", "zip", "True", "False", "None",
+)
+ALLOWED_MODULES = {"re", "json", "math", "string", "datetime"}
+
+_real_import = builtins.__import__
+
+
+def _restricted_import(name, *args, **kwargs):
+    if name.split(".")[0] not in ALLOWED_MODULES:
+        raise ImportError(f"module '{name}' is not allowed in sandboxed object macros")
+    return _real_import(name, *args, **kwargs)
+
+
+_restricted_builtins = {name: getattr(builtins, name) for name in ALLOWED_BUILTIN_NAMES if hasattr(builtins, name)}
+_restricted_builtins["__import__"] = _restricted_import
+
+macros = {}
+
+
+def rpc(method, *params):
+    print(json.dumps({"rpc": method, "params": list(params)}))
+    sys.stdout.flush()
+    reply = json.loads(sys.stdin.readline())
+    return reply.get("rpc_result")
+
+
+class _RiveScriptProxy:
+    def current_user(self):
+        return rpc("current_user")
+
+    def set_uservar(self, uid, name, value):
+        rpc("set_uservar", uid, name, value)
+
+    def get_uservar(self, uid, name):
+        return rpc("get_uservar", uid, name)
+
+
+rs = _RiveScriptProxy()
+
+for line in sys.stdin:
+    req = json.loads(line)
+    if req.get("command") == "load":
+        name, source = req["name"], req["source"]
+        body = "\n".join("    " + ln for ln in source.splitlines()) or "    pass"
+        src = f"def _macro(rs, args):\n{body}\n"
+        sandbox_globals = {"__builtins__": _restricted_builtins}
+        try:
+            exec(compile(src, f"<object {name}>", "exec"), sandbox_globals)
+            macros[name] = sandbox_globals["_macro"]
+            print(json.dumps({}))
+        except Exception as e:
+            print(json.dumps({"error": str(e)}))
+    else:
+        name, args = req["call"], req["args"]
+        try:
+            result = macros[name](rs, args)
+            print(json.dumps({"result": str(result)}))
+        except Exception as e:
+            print(json.dumps({"error": str(e)}))
+    sys.stdout.flush()
+"#;
+
+impl SandboxedPython {
+    /// Spawn the sandbox worker, running `python3 -I -S <worker_script>`
+    /// with a minimal environment.
+    pub fn spawn(worker_script: impl Into<String>) -> Result<Self, ObjectError> {
+        let worker_script = worker_script.into();
+        let mut child = Command::new("python3")
+            .args(["-I", "-S", &worker_script])
+            .env_clear()
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| ObjectError::Load(format!("could not spawn sandbox worker: {e}")))?;
+
+        let stdin = child
+            .stdin
+            .take()
+            .ok_or_else(|| ObjectError::Load("sandbox worker has no stdin".into()))?;
+        let stdout = child
+            .stdout
+            .take()
+            .ok_or_else(|| ObjectError::Load("sandbox worker has no stdout".into()))?;
+        let mut stderr_pipe = child
+            .stderr
+            .take()
+            .ok_or_else(|| ObjectError::Load("sandbox worker has no stderr".into()))?;
+
+        let stderr = Arc::new(Mutex::new(String::new()));
+        let stderr_writer = Arc::clone(&stderr);
+        thread::spawn(move || {
+            let mut buf = String::new();
+            let _ = stderr_pipe.read_to_string(&mut buf);
+            if let Ok(mut guard) = stderr_writer.lock() {
+                *guard = buf;
+            }
+        });
+
+        Ok(SandboxedPython {
+            child,
+            stdin,
+            stdout: BufReader::new(stdout),
+            worker_script,
+            stderr,
+        })
+    }
+
+    /// Spawn [`DEFAULT_WORKER_SCRIPT`] — the restricted-builtins,
+    /// import-allowlisted reference worker — writing it out under
+    /// `workdir` first. The right choice for running untrusted community
+    /// brains; bring your own worker via [`spawn`](Self::spawn) if a
+    /// macro genuinely needs a module outside the allowlist.
+    pub fn spawn_default(workdir: impl AsRef<std::path::Path>) -> Result<Self, ObjectError> {
+        let workdir = workdir.as_ref();
+        std::fs::create_dir_all(workdir)
+            .map_err(|e| ObjectError::Load(format!("could not create {}: {e}", workdir.display())))?;
+        let script_path = workdir.join("sandbox_default_worker.py");
+        std::fs::write(&script_path, DEFAULT_WORKER_SCRIPT)
+            .map_err(|e| ObjectError::Load(format!("could not write {}: {e}", script_path.display())))?;
+        Self::spawn(script_path.to_string_lossy().to_string())
+    }
+
+    fn send(&mut self, value: &Value) -> Result<(), ObjectError> {
+        let mut line = serde_json::to_string(value)
+            .map_err(|e| ObjectError::Call(format!("could not encode sandbox request: {e}")))?;
+        line.push('\n');
+        self.stdin
+            .write_all(line.as_bytes())
+            .map_err(|e| self.crash_error(e))
+    }
+
+    fn recv(&mut self) -> Result<Value, ObjectError> {
+        let mut line = String::new();
+        let n = self
+            .stdout
+            .read_line(&mut line)
+            .map_err(|e| self.crash_error(e))?;
+        if n == 0 {
+            let stderr = self.stderr.lock().map(|g| g.clone()).unwrap_or_default();
+            return Err(ObjectError::Call(if stderr.trim().is_empty() {
+                format!("sandbox worker '{}' exited unexpectedly", self.worker_script)
+            } else {
+                format!(
+                    "sandbox worker '{}' exited unexpectedly\nstderr:\n{stderr}",
+                    self.worker_script
+                )
+            }));
+        }
+        serde_json::from_str(&line)
+            .map_err(|e| ObjectError::Call(format!("malformed sandbox response: {e}")))
+    }
+
+    fn crash_error(&mut self, io_err: std::io::Error) -> ObjectError {
+        let _ = self.child.kill();
+        let stderr = self.stderr.lock().map(|g| g.clone()).unwrap_or_default();
+        if stderr.trim().is_empty() {
+            ObjectError::Call(format!("sandbox worker '{}' crashed: {io_err}", self.worker_script))
+        } else {
+            ObjectError::Call(format!(
+                "sandbox worker '{}' crashed: {io_err}\nstderr:\n{stderr}",
+                self.worker_script
+            ))
+        }
+    }
+
+    /// Service a single `{"rpc": ..., "params": [...]}` callback against
+    /// the live instance and reply with its result.
+    fn handle_rpc(&mut self, rs: &mut RiveScript, rpc: &Value) -> Result<(), ObjectError> {
+        let method = rpc
+            .get("rpc")
+            .and_then(Value::as_str)
+            .ok_or_else(|| ObjectError::Call("rpc frame missing 'rpc' field".into()))?;
+        let params = rpc
+            .get("params")
+            .and_then(Value::as_array)
+            .cloned()
+            .unwrap_or_default();
+
+        let result = match method {
+            "current_user" => json!(rs.current_user()),
+            "set_uservar" => {
+                let uid = params.first().and_then(Value::as_str).unwrap_or_default();
+                let name = params.get(1).and_then(Value::as_str).unwrap_or_default();
+                let value = params.get(2).and_then(Value::as_str).unwrap_or_default();
+                rs.set_uservar(uid, name, value);
+                Value::Null
+            }
+            "get_uservar" => {
+                let uid = params.first().and_then(Value::as_str).unwrap_or_default();
+                let name = params.get(1).and_then(Value::as_str).unwrap_or_default();
+                json!(rs.get_uservar(uid, name))
+            }
+            other => {
+                return Err(ObjectError::Call(format!("unknown sandbox rpc '{other}'")));
+            }
+        };
+
+        self.send(&json!({ "rpc_result": result }))
+    }
+}
+
+impl ObjectHandler for SandboxedPython {
+    fn load(&mut self, name: &str, code: &str) -> Result<(), ObjectError> {
+        self.send(&json!({ "command": "load", "name": name, "source": code }))?;
+        let response = self.recv()?;
+        if let Some(err) = response.get("error").and_then(Value::as_str) {
+            return Err(ObjectError::Load(err.to_string()));
+        }
+        Ok(())
+    }
+
+    fn call(&mut self, rs: &mut RiveScript, name: &str, fields: &[String]) -> Result<String, ObjectError> {
+        let uid = rs.current_user().unwrap_or_default();
+        self.send(&json!({ "call": name, "args": fields, "user": uid }))?;
+
+        // The worker may emit any number of `rpc` callback frames before
+        // it finally settles on a result or error.
+        loop {
+            let response = self.recv()?;
+            if let Some(result) = response.get("result").and_then(Value::as_str) {
+                return Ok(result.to_string());
+            }
+            if let Some(err) = response.get("error").and_then(Value::as_str) {
+                return Err(ObjectError::Call(err.to_string()));
+            }
+            if response.get("rpc").is_some() {
+                self.handle_rpc(rs, &response)?;
+                continue;
+            }
+            return Err(ObjectError::Call(format!(
+                "sandbox worker sent an unrecognised frame: {response}"
+            )));
+        }
+    }
+}
+
+impl Drop for SandboxedPython {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{PyInstance, RiveScript};
+
+    fn test_rs() -> RiveScript {
+        pyo3::Python::with_gil(|py| RiveScript::new(PyInstance::from_py_object(py.None())))
+    }
+
+    /// A worker script standing in for a real `sandbox=True` Python
+    /// object macro runner: it echoes its args back on `call` and acks
+    /// `load` unconditionally.
+    fn write_echo_worker() -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("rivescript_sandbox_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let script_path = dir.join("echo_worker.py");
+        std::fs::write(
+            &script_path,
+            "import sys, json\n\
+             for line in sys.stdin:\n\
+             \u{20}   req = json.loads(line)\n\
+             \u{20}   if req.get('command') == 'load':\n\
+             \u{20}       print(json.dumps({}))\n\
+             \u{20}   else:\n\
+             \u{20}       print(json.dumps({'result': 'echo:' + ' '.join(req['args'])}))\n\
+             \u{20}   sys.stdout.flush()\n",
+        )
+        .unwrap();
+        script_path
+    }
+
+    #[test]
+    fn load_and_call_round_trip_through_worker_process() {
+        let script_path = write_echo_worker();
+        let mut handler = SandboxedPython::spawn(script_path.to_string_lossy().to_string()).unwrap();
+
+        handler.load("echo", "return ' '.join(args)").unwrap();
+
+        let mut rs = test_rs();
+        let result = handler
+            .call(&mut rs, "echo", &["hello".to_string(), "world".to_string()])
+            .unwrap();
+        assert_eq!(result, "echo:hello world");
+    }
+
+    #[test]
+    fn default_worker_runs_allowed_macros_and_answers_rpc_callbacks() {
+        let dir = std::env::temp_dir().join(format!("rivescript_sandbox_default_test_{}", std::process::id()));
+        let mut handler = SandboxedPython::spawn_default(&dir).unwrap();
+
+        handler.load("add", "a, b = args\nreturn int(a) + int(b)").unwrap();
+        let mut rs = test_rs();
+        let result = handler.call(&mut rs, "add", &["2".to_string(), "3".to_string()]).unwrap();
+        assert_eq!(result, "5");
+
+        // `test_rs()` wraps `py.None()`, so there's no real uid to hand
+        // back, but the point of this assertion is that the worker's
+        // `rpc()` call round-trips through `handle_rpc` and gets *a*
+        // reply instead of hanging on its own `readline()`.
+        handler.load("whoami", "return str(rs.current_user())").unwrap();
+        let mut rs = test_rs();
+        let result = handler.call(&mut rs, "whoami", &[]).unwrap();
+        assert_eq!(result, "None");
+    }
+
+    #[test]
+    fn default_worker_blocks_imports_outside_the_allowlist() {
+        let dir = std::env::temp_dir().join(format!("rivescript_sandbox_default_import_test_{}", std::process::id()));
+        let mut handler = SandboxedPython::spawn_default(&dir).unwrap();
+
+        // Loading just defines the function, so the restricted import
+        // only actually fires once the macro runs.
+        handler.load("evil", "import socket\nreturn 'unreachable'").unwrap();
+        let mut rs = test_rs();
+        let err = handler.call(&mut rs, "evil", &[]).unwrap_err();
+        assert!(matches!(err, ObjectError::Call(_)));
+    }
+
+    #[test]
+    fn a_dead_worker_surfaces_as_an_object_error_not_a_hang() {
+        let dir = std::env::temp_dir().join(format!("rivescript_sandbox_test_dead_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let script_path = dir.join("exit_worker.py");
+        // Exits immediately, so the first call has nothing left to talk to.
+        std::fs::write(&script_path, "import sys\nsys.exit(0)\n").unwrap();
+
+        let mut handler = SandboxedPython::spawn(script_path.to_string_lossy().to_string()).unwrap();
+        let mut rs = test_rs();
+        let err = handler.call(&mut rs, "anything", &[]).unwrap_err();
+        assert!(matches!(err, ObjectError::Call(_)));
+    }
+}
@@ -0,0 +1,353 @@
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use serde_json::{json, Value};
+
+use crate::error::ObjectError;
+use crate::handlers::ObjectHandler;
+use crate::RiveScript;
+
+/// A reusable [`ObjectHandler`] that bridges to any external process
+/// speaking a small JSON-RPC-ish protocol over stdin/stdout.
+///
+/// `RustRiveObjects` and `SandboxedPython` are both bespoke, single
+/// purpose bridges; `SubprocessObjectHandler` is the general case, so a
+/// language like Node.js, Ruby, or Lua can get `> object ... <lang>`
+/// support with no Rust code at all:
+///
+/// ```ignore
+/// rs.set_handler("lua", SubprocessObjectHandler::spawn(&["lua", "rive_bridge.lua"])?);
+/// ```
+///
+/// Wire protocol (one JSON object per line in each direction):
+///
+/// * parent -> child, on load:
+///   `{"id": n, "command": "load", "name": ..., "source": ...}`
+/// * parent -> child, on call:
+///   `{"id": n, "command": "call", "name": ..., "args": [...], "user": uid}`
+/// * child -> parent: `{"id": n, "result": ...}` or `{"id": n, "error": ...}`
+/// * child -> parent, at any point before its matching result/error:
+///   `{"command": "callback", "method": "set_uservar", "params": [...]}`,
+///   executed against the live [`RiveScript`] instance, which then always
+///   replies with `{"command": "callback_result", "result": ...}` (with
+///   `"result": null` plus an `"error"` string, and the outstanding call
+///   failing, when the `method` is missing or unknown) so a
+///   bridge that's blocked on its own stdin waiting to read back e.g.
+///   `current_user`'s value isn't left hanging — the request that's
+///   still outstanding is only allowed to finish once the callback has
+///   been answered.
+///
+/// One subprocess is kept alive for the lifetime of the handler; requests
+/// are tagged with a monotonically increasing `id` so responses can be
+/// matched up even if the child doesn't reply in request order. A
+/// subprocess that dies mid-conversation surfaces as a clean
+/// [`ObjectError`] instead of hanging the caller.
+pub struct SubprocessObjectHandler {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    next_id: u64,
+    /// Responses that arrived out of order, keyed by request id, waiting
+    /// for their caller to ask for them.
+    pending: HashMap<u64, Value>,
+    command: Vec<String>,
+    /// The child's stderr output, collected by a background thread that
+    /// keeps draining the pipe so a noisy bridge script can never fill
+    /// the stderr pipe buffer and block on a write — which would
+    /// otherwise hang the caller blocked on `recv_one` waiting for a
+    /// stdout line that will never come. The text is stored here only
+    /// once the child closes its stderr, normally on exit.
+    stderr: Arc<Mutex<String>>,
+}
+
+impl SubprocessObjectHandler {
+    /// Spawn the bridge process, e.g. `["lua", "rive_bridge.lua"]`.
+    pub fn spawn(command: &[&str]) -> Result<Self, ObjectError> {
+        let mut parts = command.iter();
+        let program = parts
+            .next()
+            .ok_or_else(|| ObjectError::Load("subprocess handler command is empty".into()))?;
+
+        let mut child = Command::new(program)
+            .args(parts)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| ObjectError::Load(format!("could not spawn '{program}': {e}")))?;
+
+        let stdin = child
+            .stdin
+            .take()
+            .ok_or_else(|| ObjectError::Load("subprocess bridge has no stdin".into()))?;
+        let stdout = child
+            .stdout
+            .take()
+            .ok_or_else(|| ObjectError::Load("subprocess bridge has no stdout".into()))?;
+        let mut stderr_pipe = child
+            .stderr
+            .take()
+            .ok_or_else(|| ObjectError::Load("subprocess bridge has no stderr".into()))?;
+
+        let stderr = Arc::new(Mutex::new(String::new()));
+        let stderr_writer = Arc::clone(&stderr);
+        thread::spawn(move || {
+            let mut buf = String::new();
+            let _ = stderr_pipe.read_to_string(&mut buf);
+            if let Ok(mut guard) = stderr_writer.lock() {
+                *guard = buf;
+            }
+        });
+
+        Ok(SubprocessObjectHandler {
+            child,
+            stdin,
+            stdout: BufReader::new(stdout),
+            next_id: 0,
+            pending: HashMap::new(),
+            command: command.iter().map(|s| s.to_string()).collect(),
+            stderr,
+        })
+    }
+
+    fn allocate_id(&mut self) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        id
+    }
+
+    fn send(&mut self, value: &Value) -> Result<(), ObjectError> {
+        let mut line = serde_json::to_string(value)
+            .map_err(|e| ObjectError::Call(format!("could not encode bridge request: {e}")))?;
+        line.push('\n');
+        self.stdin.write_all(line.as_bytes()).map_err(|e| self.crash_error(e))
+    }
+
+    fn recv_one(&mut self) -> Result<Value, ObjectError> {
+        let mut line = String::new();
+        let n = self.stdout.read_line(&mut line).map_err(|e| self.crash_error(e))?;
+        if n == 0 {
+            let stderr = self.stderr_snapshot();
+            return Err(ObjectError::Call(if stderr.trim().is_empty() {
+                format!("subprocess bridge '{}' exited unexpectedly", self.command.join(" "))
+            } else {
+                format!(
+                    "subprocess bridge '{}' exited unexpectedly\nstderr:\n{stderr}",
+                    self.command.join(" ")
+                )
+            }));
+        }
+        serde_json::from_str(&line).map_err(|e| ObjectError::Call(format!("malformed bridge response: {e}")))
+    }
+
+    fn stderr_snapshot(&self) -> String {
+        self.stderr.lock().map(|g| g.clone()).unwrap_or_default()
+    }
+
+    fn crash_error(&mut self, io_err: std::io::Error) -> ObjectError {
+        let _ = self.child.kill();
+        let stderr = self.stderr_snapshot();
+        if stderr.trim().is_empty() {
+            ObjectError::Call(format!("subprocess bridge '{}' crashed: {io_err}", self.command.join(" ")))
+        } else {
+            ObjectError::Call(format!(
+                "subprocess bridge '{}' crashed: {io_err}\nstderr:\n{stderr}",
+                self.command.join(" ")
+            ))
+        }
+    }
+
+    /// Read frames until the one tagged `id` shows up, servicing any
+    /// `callback` frames against the live instance and stashing any
+    /// responses for other in-flight ids for later.
+    fn await_response(&mut self, rs: &mut RiveScript, id: u64) -> Result<Value, ObjectError> {
+        if let Some(response) = self.pending.remove(&id) {
+            return Ok(response);
+        }
+        loop {
+            let frame = self.recv_one()?;
+            if frame.get("command").and_then(Value::as_str) == Some("callback") {
+                self.run_callback(rs, &frame)?;
+                continue;
+            }
+            match frame.get("id").and_then(Value::as_u64) {
+                Some(frame_id) if frame_id == id => return Ok(frame),
+                Some(frame_id) => {
+                    self.pending.insert(frame_id, frame);
+                }
+                None => {
+                    return Err(ObjectError::Call(format!(
+                        "bridge response missing 'id': {frame}"
+                    )));
+                }
+            }
+        }
+    }
+
+    /// Service one `callback` frame against the live instance and always
+    /// reply with its result, mirroring `sandbox.rs`'s `handle_rpc`. A
+    /// callback that never gets a reply leaves the bridge blocked on its
+    /// own stdin read forever — exactly the hang the stderr-draining
+    /// machinery elsewhere in this file was added to avoid, just on the
+    /// other side of the pipe.
+    fn run_callback(&mut self, rs: &mut RiveScript, frame: &Value) -> Result<(), ObjectError> {
+        let method = frame.get("method").and_then(Value::as_str);
+        let params = frame.get("params").and_then(Value::as_array).cloned().unwrap_or_default();
+
+        let result = match method {
+            Some("current_user") => json!(rs.current_user()),
+            Some("set_uservar") => {
+                let uid = params.first().and_then(Value::as_str).unwrap_or_default();
+                let name = params.get(1).and_then(Value::as_str).unwrap_or_default();
+                let value = params.get(2).and_then(Value::as_str).unwrap_or_default();
+                rs.set_uservar(uid, name, value);
+                Value::Null
+            }
+            Some("get_uservar") => {
+                let uid = params.first().and_then(Value::as_str).unwrap_or_default();
+                let name = params.get(1).and_then(Value::as_str).unwrap_or_default();
+                json!(rs.get_uservar(uid, name))
+            }
+            other => {
+                let msg = match other {
+                    Some(m) => format!("unknown bridge callback '{m}'"),
+                    None => "callback frame missing 'method'".to_string(),
+                };
+                self.send(&json!({ "command": "callback_result", "result": Value::Null, "error": msg }))?;
+                return Err(ObjectError::Call(msg));
+            }
+        };
+
+        self.send(&json!({ "command": "callback_result", "result": result }))
+    }
+}
+
+impl ObjectHandler for SubprocessObjectHandler {
+    fn load(&mut self, name: &str, code: &str) -> Result<(), ObjectError> {
+        let id = self.allocate_id();
+        self.send(&json!({ "id": id, "command": "load", "name": name, "source": code }))?;
+
+        // `load` never triggers callbacks, but we still need a live `rs`
+        // to satisfy `await_response`'s signature; an empty instance
+        // would be wrong, so we drain frames directly instead.
+        loop {
+            let frame = self.recv_one()?;
+            match frame.get("id").and_then(Value::as_u64) {
+                Some(frame_id) if frame_id == id => {
+                    if let Some(err) = frame.get("error").and_then(Value::as_str) {
+                        return Err(ObjectError::Load(err.to_string()));
+                    }
+                    return Ok(());
+                }
+                Some(frame_id) => {
+                    self.pending.insert(frame_id, frame);
+                }
+                None => {
+                    return Err(ObjectError::Load(format!("bridge response missing 'id': {frame}")));
+                }
+            }
+        }
+    }
+
+    fn call(&mut self, rs: &mut RiveScript, name: &str, fields: &[String]) -> Result<String, ObjectError> {
+        let id = self.allocate_id();
+        let uid = rs.current_user().unwrap_or_default();
+        self.send(&json!({ "id": id, "command": "call", "name": name, "args": fields, "user": uid }))?;
+
+        let response = self.await_response(rs, id)?;
+        match response.get("result") {
+            Some(Value::String(result)) => return Ok(result.clone()),
+            Some(Value::Null) | None => {}
+            // Non-string results (numbers, booleans, ...) become their JSON text.
+            Some(other) => return Ok(other.to_string()),
+        }
+        if let Some(err) = response.get("error").and_then(Value::as_str) {
+            return Err(ObjectError::Call(err.to_string()));
+        }
+        Err(ObjectError::Call(format!("bridge response had neither result nor error: {response}")))
+    }
+}
+
+impl Drop for SubprocessObjectHandler {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{PyInstance, RiveScript};
+
+    fn test_rs() -> RiveScript {
+        pyo3::Python::with_gil(|py| RiveScript::new(PyInstance::from_py_object(py.None())))
+    }
+
+    /// A bridge script standing in for a real language adapter: it acks
+    /// `load`, and on `call` emits a `set_uservar` callback frame and a
+    /// `current_user` callback frame — reading back the latter's reply
+    /// and folding it into the result — before replying with the echoed
+    /// args, so the test exercises both frame kinds and proves callbacks
+    /// actually get answered instead of leaving the bridge blocked.
+    fn write_echo_bridge() -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("rivescript_subprocess_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let script_path = dir.join("echo_bridge.py");
+        std::fs::write(
+            &script_path,
+            "import sys, json\n\
+             for line in sys.stdin:\n\
+             \u{20}   req = json.loads(line)\n\
+             \u{20}   rid = req.get('id')\n\
+             \u{20}   if req.get('command') == 'load':\n\
+             \u{20}       print(json.dumps({'id': rid}))\n\
+             \u{20}       sys.stdout.flush()\n\
+             \u{20}       continue\n\
+             \u{20}   print(json.dumps({'command': 'callback', 'method': 'set_uservar', 'params': ['alice', 'seen', 'yes']}))\n\
+             \u{20}   sys.stdout.flush()\n\
+             \u{20}   sys.stdin.readline()\n\
+             \u{20}   print(json.dumps({'command': 'callback', 'method': 'current_user', 'params': []}))\n\
+             \u{20}   sys.stdout.flush()\n\
+             \u{20}   reply = json.loads(sys.stdin.readline())\n\
+             \u{20}   uid = reply.get('result') or 'nobody'\n\
+             \u{20}   print(json.dumps({'id': rid, 'result': 'echo:' + uid + ':' + ' '.join(req['args'])}))\n\
+             \u{20}   sys.stdout.flush()\n",
+        )
+        .unwrap();
+        script_path
+    }
+
+    #[test]
+    fn load_and_call_round_trip_with_a_callback_frame() {
+        let script_path = write_echo_bridge();
+        let mut handler = SubprocessObjectHandler::spawn(&["python3", &script_path.to_string_lossy()]).unwrap();
+
+        handler.load("echo", "return ' '.join(args)").unwrap();
+
+        let mut rs = test_rs();
+        let result = handler
+            .call(&mut rs, "echo", &["hello".to_string(), "world".to_string()])
+            .unwrap();
+        // `test_rs()` wraps `py.None()`, so `current_user()` has nothing
+        // to extract and the bridge sees `null` -> falls back to
+        // 'nobody'; the point is that it got *a* reply at all instead of
+        // hanging on its own `readline()`.
+        assert_eq!(result, "echo:nobody:hello world");
+    }
+
+    #[test]
+    fn a_dead_bridge_surfaces_as_an_object_error_not_a_hang() {
+        let dir = std::env::temp_dir().join(format!("rivescript_subprocess_test_dead_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let script_path = dir.join("exit_bridge.py");
+        std::fs::write(&script_path, "import sys\nsys.exit(0)\n").unwrap();
+
+        let mut handler = SubprocessObjectHandler::spawn(&["python3", &script_path.to_string_lossy()]).unwrap();
+        let mut rs = test_rs();
+        let err = handler.call(&mut rs, "anything", &[]).unwrap_err();
+        assert!(matches!(err, ObjectError::Call(_)));
+    }
+}
@@ -0,0 +1,112 @@
+//! `rivescript_native`: a PyO3 companion crate for `rivescript-python`.
+//!
+//! The Python package owns the interpreter, brain loader, and reply
+//! engine; this crate exists purely to give it places to drop down into
+//! native code when the pure-Python path is too slow or needs isolation
+//! it can't easily provide on its own (compiled object macros, sandboxed
+//! macro execution, an out-of-process bridge for other scripting
+//! languages, an accelerated trigger matcher, and the `stats` builtin
+//! macro library). Everything here is optional: the Python side always
+//! has a pure-Python fallback and only reaches for this module when it
+//! is present and importable.
+
+pub mod error;
+pub mod handlers;
+pub mod matching;
+pub mod stats;
+
+pub use error::ObjectError;
+pub use handlers::ObjectHandler;
+
+/// The `rivescript_native` Python extension module. Importable on its
+/// own (`import rivescript_native`); the Python side treats a failed
+/// import as "accelerator not installed" and falls back to pure Python.
+#[pyo3::pymodule]
+fn rivescript_native(py: pyo3::Python<'_>, m: &pyo3::types::PyModule) -> pyo3::PyResult<()> {
+    matching::register(py, m)?;
+    stats::register(py, m)
+}
+
+/// A minimal view of the live `RiveScript` interpreter instance, as seen
+/// from native code.
+///
+/// The real `RiveScript` object lives on the Python side. Handlers in
+/// this crate receive it as a `&mut RiveScript` (a thin Rust-side proxy
+/// wrapping the `PyObject`) so that object macros compiled or executed
+/// natively can still call back into the live bot the same way a plain
+/// Python macro would via `rs.current_user()` / `rs.set_uservar()`.
+pub struct RiveScript {
+    inner: PyInstance,
+}
+
+impl RiveScript {
+    /// Wrap a live interpreter instance.
+    pub fn new(inner: PyInstance) -> Self {
+        RiveScript { inner }
+    }
+
+    /// Mirrors the Python `RiveScript.current_user()` method: the UID of
+    /// the user currently being replied to.
+    pub fn current_user(&self) -> Option<String> {
+        self.inner.current_user()
+    }
+
+    /// Mirrors the Python `RiveScript.set_uservar(uid, name, value)`
+    /// method.
+    pub fn set_uservar(&mut self, uid: &str, name: &str, value: &str) {
+        self.inner.set_uservar(uid, name, value);
+    }
+
+    /// Mirrors the Python `RiveScript.get_uservar(uid, name)` method.
+    pub fn get_uservar(&self, uid: &str, name: &str) -> Option<String> {
+        self.inner.get_uservar(uid, name)
+    }
+
+    /// The raw Python `RiveScript` object, for handlers that hand the
+    /// live instance straight to a compiled or scripted macro instead of
+    /// going through the typed accessors above.
+    pub(crate) fn as_py_object(&self, py: pyo3::Python<'_>) -> pyo3::PyObject {
+        self.inner.obj.clone_ref(py)
+    }
+}
+
+/// Opaque handle to the Python-side `RiveScript` object.
+///
+/// Kept separate from [`RiveScript`] so that the PyO3 glue (borrowing the
+/// GIL, downcasting `PyAny`, etc.) stays in one place instead of being
+/// repeated in every handler.
+pub struct PyInstance {
+    obj: pyo3::PyObject,
+}
+
+impl PyInstance {
+    pub fn from_py_object(obj: pyo3::PyObject) -> Self {
+        PyInstance { obj }
+    }
+
+    fn current_user(&self) -> Option<String> {
+        pyo3::Python::with_gil(|py| {
+            self.obj
+                .call_method0(py, "current_user")
+                .ok()
+                .and_then(|v| v.extract::<Option<String>>(py).ok())
+                .flatten()
+        })
+    }
+
+    fn set_uservar(&mut self, uid: &str, name: &str, value: &str) {
+        pyo3::Python::with_gil(|py| {
+            let _ = self.obj.call_method1(py, "set_uservar", (uid, name, value));
+        });
+    }
+
+    fn get_uservar(&self, uid: &str, name: &str) -> Option<String> {
+        pyo3::Python::with_gil(|py| {
+            self.obj
+                .call_method1(py, "get_uservar", (uid, name))
+                .ok()
+                .and_then(|v| v.extract::<Option<String>>(py).ok())
+                .flatten()
+        })
+    }
+}
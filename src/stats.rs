@@ -0,0 +1,438 @@
+//! Streaming statistics backing the `stats` builtin macro library.
+//!
+//! `rs.load_builtins("stats")` (Python side) registers a handful of
+//! object macros — `stats_mean`, `stats_ewma`, `stats_minmax`,
+//! `stats_quantile` — that a bot author calls from a reply, e.g.
+//! `<call>stats_quantile response_time 0.9</call>`. Each macro keeps its
+//! running state in a single user variable (so it survives across turns
+//! without storing full per-user histories) by round-tripping one of the
+//! estimators below through [`Estimator::save`] / [`Estimator::load`].
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+/// The estimators below sort `f64` observations with `partial_cmp(...)
+/// .unwrap()`, which panics on `NaN` (its ordering is undefined). A
+/// `<call>` macro argument is parsed straight from user-controlled text,
+/// and Python's `float()` happily accepts `"nan"`/`"inf"`, so every
+/// `stats_*` entry point rejects a non-finite `value` up front instead of
+/// letting it reach a sort.
+fn require_finite(value: f64) -> PyResult<()> {
+    if value.is_finite() {
+        Ok(())
+    } else {
+        Err(PyValueError::new_err(format!(
+            "stats value must be finite, got {value}"
+        )))
+    }
+}
+
+/// Something that turns into a user-variable string and back, so its
+/// running state can be stashed with `rs.set_uservar` between calls.
+pub trait Estimator: Sized {
+    fn save(&self) -> String;
+    fn load(state: &str) -> Self;
+}
+
+/// A running (cumulative) mean, O(1) space.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RunningMean {
+    pub count: u64,
+    pub mean: f64,
+}
+
+impl RunningMean {
+    pub fn update(&mut self, value: f64) -> f64 {
+        self.count += 1;
+        self.mean += (value - self.mean) / self.count as f64;
+        self.mean
+    }
+}
+
+impl Estimator for RunningMean {
+    fn save(&self) -> String {
+        format!("{}:{}", self.count, self.mean)
+    }
+
+    fn load(state: &str) -> Self {
+        let mut parts = state.splitn(2, ':');
+        let count = parts.next().and_then(|s| s.parse().ok()).unwrap_or(0);
+        let mean = parts.next().and_then(|s| s.parse().ok()).unwrap_or(0.0);
+        RunningMean { count, mean }
+    }
+}
+
+/// An exponentially-weighted moving mean and variance.
+#[derive(Clone, Copy, Debug)]
+pub struct Ewma {
+    pub alpha: f64,
+    pub mean: f64,
+    pub variance: f64,
+    pub initialized: bool,
+}
+
+impl Ewma {
+    pub fn new(alpha: f64) -> Self {
+        Ewma { alpha, mean: 0.0, variance: 0.0, initialized: false }
+    }
+
+    pub fn update(&mut self, value: f64) -> (f64, f64) {
+        if !self.initialized {
+            self.mean = value;
+            self.variance = 0.0;
+            self.initialized = true;
+        } else {
+            let delta = value - self.mean;
+            self.mean += self.alpha * delta;
+            self.variance = (1.0 - self.alpha) * (self.variance + self.alpha * delta * delta);
+        }
+        (self.mean, self.variance)
+    }
+}
+
+impl Estimator for Ewma {
+    fn save(&self) -> String {
+        format!("{}:{}:{}:{}", self.alpha, self.mean, self.variance, self.initialized as u8)
+    }
+
+    fn load(state: &str) -> Self {
+        let mut parts = state.splitn(4, ':');
+        let alpha = parts.next().and_then(|s| s.parse().ok()).unwrap_or(0.3);
+        let mean = parts.next().and_then(|s| s.parse().ok()).unwrap_or(0.0);
+        let variance = parts.next().and_then(|s| s.parse().ok()).unwrap_or(0.0);
+        let initialized = parts.next().and_then(|s| s.parse::<u8>().ok()).unwrap_or(0) != 0;
+        Ewma { alpha, mean, variance, initialized }
+    }
+}
+
+/// Running min/max (peak-to-peak range), O(1) space.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MinMax {
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+}
+
+impl MinMax {
+    pub fn update(&mut self, value: f64) {
+        self.min = Some(self.min.map_or(value, |m| m.min(value)));
+        self.max = Some(self.max.map_or(value, |m| m.max(value)));
+    }
+
+    pub fn range(&self) -> Option<f64> {
+        Some(self.max? - self.min?)
+    }
+}
+
+impl Estimator for MinMax {
+    fn save(&self) -> String {
+        format!(
+            "{}:{}",
+            self.min.map_or(String::new(), |v| v.to_string()),
+            self.max.map_or(String::new(), |v| v.to_string()),
+        )
+    }
+
+    fn load(state: &str) -> Self {
+        let mut parts = state.splitn(2, ':');
+        let min = parts.next().and_then(|s| s.parse().ok());
+        let max = parts.next().and_then(|s| s.parse().ok());
+        MinMax { min, max }
+    }
+}
+
+/// The P² (Jain & Chlamtac) streaming quantile estimator: tracks a
+/// single quantile `p` in O(1) space and time per observation using five
+/// markers (min, two interior, max, and the target quantile) whose
+/// heights are adjusted with a parabolic fit each step, falling back to
+/// linear interpolation whenever the parabolic prediction would break
+/// the markers' monotonic ordering.
+#[derive(Clone, Copy, Debug)]
+pub struct P2Quantile {
+    p: f64,
+    count: usize,
+    heights: [f64; 5],
+    positions: [f64; 5],
+    desired: [f64; 5],
+    increments: [f64; 5],
+}
+
+impl P2Quantile {
+    pub fn new(p: f64) -> Self {
+        P2Quantile {
+            p,
+            count: 0,
+            heights: [0.0; 5],
+            positions: [1.0, 2.0, 3.0, 4.0, 5.0],
+            desired: [1.0, 1.0 + 2.0 * p, 1.0 + 4.0 * p, 3.0 + 2.0 * p, 5.0],
+            increments: [0.0, p / 2.0, p, (1.0 + p) / 2.0, 1.0],
+        }
+    }
+
+    pub fn update(&mut self, value: f64) -> f64 {
+        if self.count < 5 {
+            self.heights[self.count] = value;
+            self.count += 1;
+            if self.count == 5 {
+                self.heights.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            }
+            return self.heights[self.count - 1];
+        }
+
+        // Find the cell k such that heights[k] <= value < heights[k+1],
+        // nudging the outer markers if value lands outside the range.
+        let mut k = 0usize;
+        if value < self.heights[0] {
+            self.heights[0] = value;
+            k = 0;
+        } else if value >= self.heights[4] {
+            self.heights[4] = value;
+            k = 3;
+        } else {
+            for i in 0..4 {
+                if self.heights[i] <= value && value < self.heights[i + 1] {
+                    k = i;
+                    break;
+                }
+            }
+        }
+        for i in (k + 1)..5 {
+            self.positions[i] += 1.0;
+        }
+        for i in 0..5 {
+            self.desired[i] += self.increments[i];
+        }
+
+        for i in 1..4 {
+            let d = self.desired[i] - self.positions[i];
+            if (d >= 1.0 && self.positions[i + 1] - self.positions[i] > 1.0)
+                || (d <= -1.0 && self.positions[i - 1] - self.positions[i] < -1.0)
+            {
+                let sign = if d >= 0.0 { 1.0 } else { -1.0 };
+                let parabolic = self.parabolic(i, sign);
+                self.heights[i] = if self.heights[i - 1] < parabolic && parabolic < self.heights[i + 1] {
+                    parabolic
+                } else {
+                    self.linear(i, sign)
+                };
+                self.positions[i] += sign;
+            }
+        }
+
+        self.heights[2]
+    }
+
+    fn parabolic(&self, i: usize, d: f64) -> f64 {
+        let (q, n, np1, nm1) = (self.heights[i], self.positions[i], self.positions[i + 1], self.positions[i - 1]);
+        let (qp1, qm1) = (self.heights[i + 1], self.heights[i - 1]);
+        q + d / (np1 - nm1)
+            * ((n - nm1 + d) * (qp1 - q) / (np1 - n) + (np1 - n - d) * (q - qm1) / (n - nm1))
+    }
+
+    fn linear(&self, i: usize, d: f64) -> f64 {
+        let target = (i as isize + d as isize) as usize;
+        self.heights[i] + d * (self.heights[target] - self.heights[i]) / (self.positions[target] - self.positions[i])
+    }
+
+    /// The current estimate of the `p`-th quantile.
+    pub fn estimate(&self) -> f64 {
+        if self.count == 0 {
+            return 0.0;
+        }
+        if self.count < 5 {
+            let idx = ((self.p * (self.count - 1) as f64).round() as usize).min(self.count - 1);
+            let mut sorted: Vec<f64> = self.heights[..self.count].to_vec();
+            sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            return sorted[idx];
+        }
+        self.heights[2]
+    }
+}
+
+impl Estimator for P2Quantile {
+    fn save(&self) -> String {
+        let floats: Vec<String> = self.heights.iter().chain(self.positions.iter()).chain(self.desired.iter()).chain(self.increments.iter()).map(|v| v.to_string()).collect();
+        format!("{}:{}:{}", self.p, self.count, floats.join(","))
+    }
+
+    fn load(state: &str) -> Self {
+        let mut parts = state.splitn(3, ':');
+        let p = parts.next().and_then(|s| s.parse().ok()).unwrap_or(0.5);
+        let count = parts.next().and_then(|s| s.parse().ok()).unwrap_or(0);
+        let floats: Vec<f64> = parts
+            .next()
+            .unwrap_or_default()
+            .split(',')
+            .filter_map(|s| s.parse().ok())
+            .collect();
+
+        let mut q = P2Quantile::new(p);
+        q.count = count;
+        if floats.len() == 20 {
+            q.heights.copy_from_slice(&floats[0..5]);
+            q.positions.copy_from_slice(&floats[5..10]);
+            q.desired.copy_from_slice(&floats[10..15]);
+            q.increments.copy_from_slice(&floats[15..20]);
+        }
+        q
+    }
+}
+
+/// `stats_mean(state, value) -> (new_state, mean)`, the native backing
+/// of the `stats_mean` builtin macro. Raises `ValueError` if `value` is
+/// `NaN` or infinite.
+#[pyfunction]
+fn stats_mean(state: &str, value: f64) -> PyResult<(String, f64)> {
+    require_finite(value)?;
+    let mut estimator = if state.is_empty() { RunningMean::default() } else { RunningMean::load(state) };
+    let mean = estimator.update(value);
+    Ok((estimator.save(), mean))
+}
+
+/// `stats_ewma(state, alpha, value) -> (new_state, mean, variance)`.
+/// Raises `ValueError` if `value` is `NaN` or infinite.
+#[pyfunction]
+fn stats_ewma(state: &str, alpha: f64, value: f64) -> PyResult<(String, f64, f64)> {
+    require_finite(value)?;
+    let mut estimator = if state.is_empty() { Ewma::new(alpha) } else { Ewma::load(state) };
+    let (mean, variance) = estimator.update(value);
+    Ok((estimator.save(), mean, variance))
+}
+
+/// `stats_minmax(state, value) -> (new_state, min, max)`. Raises
+/// `ValueError` if `value` is `NaN` or infinite.
+#[pyfunction]
+fn stats_minmax(state: &str, value: f64) -> PyResult<(String, f64, f64)> {
+    require_finite(value)?;
+    let mut estimator = if state.is_empty() { MinMax::default() } else { MinMax::load(state) };
+    estimator.update(value);
+    Ok((estimator.save(), estimator.min.unwrap_or(value), estimator.max.unwrap_or(value)))
+}
+
+/// `stats_quantile(state, p, value) -> (new_state, estimate)`. Raises
+/// `ValueError` if `value` is `NaN` or infinite.
+#[pyfunction]
+fn stats_quantile(state: &str, p: f64, value: f64) -> PyResult<(String, f64)> {
+    require_finite(value)?;
+    let mut estimator = if state.is_empty() { P2Quantile::new(p) } else { P2Quantile::load(state) };
+    estimator.update(value);
+    let estimate = estimator.estimate();
+    Ok((estimator.save(), estimate))
+}
+
+/// Registers the `stats_*` functions on the `rivescript_native` module.
+pub fn register(_py: Python<'_>, m: &PyModule) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(stats_mean, m)?)?;
+    m.add_function(wrap_pyfunction!(stats_ewma, m)?)?;
+    m.add_function(wrap_pyfunction!(stats_minmax, m)?)?;
+    m.add_function(wrap_pyfunction!(stats_quantile, m)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn running_mean_matches_the_arithmetic_mean() {
+        let mut m = RunningMean::default();
+        for v in [2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0] {
+            m.update(v);
+        }
+        assert!((m.mean - 5.0).abs() < 1e-9);
+        assert_eq!(m.count, 8);
+    }
+
+    #[test]
+    fn running_mean_round_trips_through_save_load() {
+        let mut m = RunningMean::default();
+        m.update(10.0);
+        m.update(20.0);
+        let restored = RunningMean::load(&m.save());
+        assert_eq!(restored.count, m.count);
+        assert!((restored.mean - m.mean).abs() < 1e-9);
+    }
+
+    #[test]
+    fn ewma_converges_toward_a_constant_stream() {
+        let mut ewma = Ewma::new(0.5);
+        let mut last = 0.0;
+        for _ in 0..20 {
+            let (mean, _variance) = ewma.update(10.0);
+            last = mean;
+        }
+        assert!((last - 10.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn minmax_tracks_the_observed_range() {
+        let mut mm = MinMax::default();
+        for v in [3.0, -1.0, 7.0, 2.0] {
+            mm.update(v);
+        }
+        assert_eq!(mm.min, Some(-1.0));
+        assert_eq!(mm.max, Some(7.0));
+        assert_eq!(mm.range(), Some(8.0));
+    }
+
+    #[test]
+    fn p2_quantile_median_matches_textbook_worked_example() {
+        // The sequence and expected running median from Jain & Chlamtac's
+        // original P^2 paper (1985), Table 1: after these 20 observations
+        // the estimated median is ~4.44.
+        let observations = [
+            0.02, 0.15, 0.74, 3.39, 0.83, 22.37, 10.15, 15.43, 38.62, 15.92, 34.60, 10.28, 1.47,
+            0.40, 0.05, 11.39, 0.27, 0.42, 0.09, 11.37,
+        ];
+        let mut q = P2Quantile::new(0.5);
+        for v in observations {
+            q.update(v);
+        }
+        assert!((q.estimate() - 4.44).abs() < 0.5, "got {}", q.estimate());
+    }
+
+    #[test]
+    fn p2_quantile_round_trips_through_save_load() {
+        let mut q = P2Quantile::new(0.9);
+        for v in [1.0, 5.0, 3.0, 9.0, 2.0, 8.0, 4.0, 7.0, 6.0, 10.0] {
+            q.update(v);
+        }
+        let restored = P2Quantile::load(&q.save());
+        assert!((restored.estimate() - q.estimate()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn require_finite_rejects_nan_and_infinity_but_accepts_ordinary_values() {
+        assert!(require_finite(1.5).is_ok());
+        assert!(require_finite(f64::NAN).is_err());
+        assert!(require_finite(f64::INFINITY).is_err());
+        assert!(require_finite(f64::NEG_INFINITY).is_err());
+    }
+
+    #[test]
+    fn stats_mean_rejects_nan_instead_of_panicking() {
+        assert!(stats_mean("", f64::NAN).is_err());
+    }
+
+    #[test]
+    fn stats_quantile_rejects_nan_instead_of_panicking() {
+        // With four samples already in, a NaN fifth sample would reach
+        // `P2Quantile::update`'s `partial_cmp(...).unwrap()` sort and
+        // panic there without the `require_finite` guard.
+        let mut state = String::new();
+        for v in [1.0, 2.0, 3.0, 4.0] {
+            state = stats_quantile(&state, 0.5, v).unwrap().0;
+        }
+        assert!(stats_quantile(&state, 0.5, f64::NAN).is_err());
+    }
+
+    #[test]
+    fn p2_quantile_with_few_samples_falls_back_to_sorted_lookup() {
+        let mut q = P2Quantile::new(0.5);
+        q.update(1.0);
+        q.update(3.0);
+        q.update(2.0);
+        // Fewer than 5 samples: estimate() should behave like an ordinary
+        // percentile of what's been seen so far, not the marker scheme.
+        assert!((q.estimate() - 2.0).abs() < 1e-9);
+    }
+}